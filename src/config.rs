@@ -0,0 +1,473 @@
+//! Layered `.restackrc` configuration.
+//!
+//! Settings are read from, in increasing priority order: a system-wide file
+//! (`/etc/restack/config`), a per-user file (`$XDG_CONFIG_HOME/restack/config`
+//! if that variable is set, else `~/.config/restack/config`), and
+//! a repo file (`.restackrc`, found by walking up from the repo root). Each
+//! layer may `%include` other files and `%unset` a key set by a lower
+//! layer. The result only ever supplies *defaults* — CLI flags always win.
+
+use anyhow::{Context, Result, bail};
+use console::Color;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// Resolved settings, still optional: `None` means "not set by any layer".
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    pub no_push: Option<bool>,
+    pub pr_fetch_limit: Option<u32>,
+    pub branch_palette: Option<Vec<Color>>,
+    pub remote: Option<String>,
+}
+
+/// A single parsed line, carrying enough to apply it in file order.
+#[derive(Debug, Clone)]
+enum Directive {
+    Set {
+        section: String,
+        key: String,
+        value: String,
+    },
+    Unset {
+        section: String,
+        key: String,
+    },
+}
+
+/// Parses one config file, inlining `%include`d files at the point they
+/// appear. `visited` guards against include cycles across the whole call
+/// chain (not just within one file).
+fn parse_file(path: &Path, visited: &mut HashSet<PathBuf>) -> Result<Vec<Directive>> {
+    let canonical = path
+        .canonicalize()
+        .with_context(|| format!("failed to resolve config file '{}'", path.display()))?;
+    if !visited.insert(canonical.clone()) {
+        bail!("circular %include detected at '{}'", path.display());
+    }
+
+    let contents = std::fs::read_to_string(&canonical)
+        .with_context(|| format!("failed to read config file '{}'", canonical.display()))?;
+    let dir = canonical
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| PathBuf::from("."));
+
+    let mut directives = Vec::new();
+    let mut section = String::new();
+    let mut last_key: Option<String> = None;
+
+    for line in contents.lines() {
+        if line.trim().is_empty() {
+            last_key = None;
+            continue;
+        }
+
+        if line.starts_with(|c: char| c.is_whitespace())
+            && last_key.is_some()
+            && let Some(Directive::Set { value, .. }) = directives.last_mut()
+        {
+            if !value.is_empty() {
+                value.push('\n');
+            }
+            value.push_str(line.trim());
+            continue;
+        }
+
+        let trimmed = line.trim();
+
+        if trimmed.starts_with('#') || trimmed.starts_with(';') {
+            last_key = None;
+            continue;
+        }
+
+        if let Some(rest) = trimmed.strip_prefix("%include ") {
+            let include_path = dir.join(rest.trim());
+            directives.extend(parse_file(&include_path, visited)?);
+            last_key = None;
+            continue;
+        }
+
+        if let Some(rest) = trimmed.strip_prefix("%unset ") {
+            directives.push(Directive::Unset {
+                section: section.clone(),
+                key: rest.trim().to_string(),
+            });
+            last_key = None;
+            continue;
+        }
+
+        if trimmed.starts_with('[') && trimmed.ends_with(']') && trimmed.len() >= 2 {
+            let inner = &trimmed[1..trimmed.len() - 1];
+            if !inner.contains('[') {
+                section = inner.to_string();
+                last_key = None;
+                continue;
+            }
+        }
+
+        if let Some(eq_idx) = trimmed.find('=') {
+            let key = trimmed[..eq_idx].trim();
+            let value = trimmed[eq_idx + 1..].trim();
+            if !key.is_empty() && !key.starts_with('=') {
+                directives.push(Directive::Set {
+                    section: section.clone(),
+                    key: key.to_string(),
+                    value: value.to_string(),
+                });
+                last_key = Some(key.to_string());
+                continue;
+            }
+        }
+
+        bail!("malformed config line in '{}': {line}", canonical.display());
+    }
+
+    // Pop this file off the include chain, so another file in the same
+    // layer may `%include` it again (a repeat, not a cycle).
+    visited.remove(&canonical);
+
+    Ok(directives)
+}
+
+/// Applies a layer's directives onto the running resolved map, in order, so
+/// a later `%unset` can remove a key a earlier directive in the same layer
+/// (or a prior layer) set.
+fn apply_directives(resolved: &mut HashMap<String, String>, directives: &[Directive]) {
+    for directive in directives {
+        match directive {
+            Directive::Set {
+                section,
+                key,
+                value,
+            } => {
+                resolved.insert(composite_key(section, key), value.clone());
+            }
+            Directive::Unset { section, key } => {
+                resolved.remove(&composite_key(section, key));
+                // `lookup` treats a bare key and `core.<key>` as the same
+                // setting when reading, so an `%unset` in either of those
+                // two contexts must clear both forms to actually take effect.
+                if section.is_empty() || section == "core" {
+                    resolved.remove(&composite_key("core", key));
+                    resolved.remove(key);
+                }
+            }
+        }
+    }
+}
+
+/// Settings live under `[core]` by convention (e.g. `no_push`, `remote`),
+/// but an un-sectioned file is also accepted for simple one-off overrides.
+fn composite_key(section: &str, key: &str) -> String {
+    if section.is_empty() {
+        key.to_string()
+    } else {
+        format!("{section}.{key}")
+    }
+}
+
+fn user_config_path() -> Option<PathBuf> {
+    if let Ok(xdg) = std::env::var("XDG_CONFIG_HOME") {
+        return Some(PathBuf::from(xdg).join("restack/config"));
+    }
+    std::env::var("HOME")
+        .ok()
+        .map(|home| PathBuf::from(home).join(".config/restack/config"))
+}
+
+/// Walks up from `repo_root` looking for `.restackrc`, the same way tools
+/// like `.editorconfig` search: the repo root itself first, then each
+/// parent, stopping at the filesystem root.
+fn find_repo_config(repo_root: &Path) -> Option<PathBuf> {
+    let mut dir = Some(repo_root);
+    while let Some(d) = dir {
+        let candidate = d.join(".restackrc");
+        if candidate.is_file() {
+            return Some(candidate);
+        }
+        dir = d.parent();
+    }
+    None
+}
+
+fn layer_paths(repo_root: &Path) -> Vec<PathBuf> {
+    let mut paths = vec![PathBuf::from("/etc/restack/config")];
+    paths.extend(user_config_path());
+    paths.extend(find_repo_config(repo_root));
+    paths
+}
+
+fn parse_color(name: &str) -> Result<Color> {
+    match name.to_lowercase().as_str() {
+        "black" => Ok(Color::Black),
+        "red" => Ok(Color::Red),
+        "green" => Ok(Color::Green),
+        "yellow" => Ok(Color::Yellow),
+        "blue" => Ok(Color::Blue),
+        "magenta" => Ok(Color::Magenta),
+        "cyan" => Ok(Color::Cyan),
+        "white" => Ok(Color::White),
+        other => bail!("unknown color '{other}' in branch palette"),
+    }
+}
+
+impl Config {
+    /// Loads and merges all layers found for the repo at `repo_root`.
+    /// Missing layers are silently skipped; a present-but-unreadable or
+    /// malformed layer is an error.
+    pub fn load(repo_root: &Path) -> Result<Config> {
+        let mut resolved: HashMap<String, String> = HashMap::new();
+
+        for path in layer_paths(repo_root) {
+            if !path.is_file() {
+                continue;
+            }
+            let mut visited = HashSet::new();
+            let directives = parse_file(&path, &mut visited)
+                .with_context(|| format!("failed to parse config layer '{}'", path.display()))?;
+            apply_directives(&mut resolved, &directives);
+        }
+
+        let lookup = |key: &str| -> Option<&String> {
+            resolved
+                .get(&composite_key("core", key))
+                .or_else(|| resolved.get(key))
+        };
+
+        let no_push = match lookup("no_push").map(String::as_str) {
+            None => None,
+            Some("true" | "1" | "yes") => Some(true),
+            Some("false" | "0" | "no") => Some(false),
+            Some(other) => bail!("invalid value for 'no_push': '{other}'"),
+        };
+
+        let pr_fetch_limit = lookup("pr_fetch_limit")
+            .map(|v| {
+                v.parse::<u32>()
+                    .with_context(|| format!("invalid value for 'pr_fetch_limit': '{v}'"))
+            })
+            .transpose()?;
+
+        let branch_palette = lookup("palette")
+            .map(|v| v.split(',').map(|s| parse_color(s.trim())).collect())
+            .transpose()?;
+
+        let remote = lookup("remote").cloned();
+
+        Ok(Config {
+            no_push,
+            pr_fetch_limit,
+            branch_palette,
+            remote,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A scratch directory under the OS temp dir, removed on drop.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir()
+                .join(format!("restack-config-test-{}-{name}", std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+
+        fn write(&self, relative: &str, contents: &str) -> PathBuf {
+            let path = self.0.join(relative);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent).unwrap();
+            }
+            std::fs::write(&path, contents).unwrap();
+            path
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn parses_sections_and_key_values() {
+        let dir = TempDir::new("basic");
+        let path = dir.write(
+            "config",
+            "\
+[core]
+no_push = true
+remote = upstream
+",
+        );
+        let mut visited = HashSet::new();
+        let directives = parse_file(&path, &mut visited).unwrap();
+        let mut resolved = HashMap::new();
+        apply_directives(&mut resolved, &directives);
+        assert_eq!(resolved.get("core.no_push"), Some(&"true".to_string()));
+        assert_eq!(resolved.get("core.remote"), Some(&"upstream".to_string()));
+    }
+
+    #[test]
+    fn ignores_comments_and_blank_lines() {
+        let dir = TempDir::new("comments");
+        let path = dir.write(
+            "config",
+            "\
+# a comment
+; also a comment
+
+remote = origin
+",
+        );
+        let mut visited = HashSet::new();
+        let directives = parse_file(&path, &mut visited).unwrap();
+        let mut resolved = HashMap::new();
+        apply_directives(&mut resolved, &directives);
+        assert_eq!(resolved.get("remote"), Some(&"origin".to_string()));
+    }
+
+    #[test]
+    fn later_layer_unsets_earlier_value() {
+        let dir = TempDir::new("unset");
+        let base = dir.write("base", "[core]\nno_push = true\n");
+        let override_file = dir.write("override", "[core]\n%unset no_push\n");
+
+        let mut resolved = HashMap::new();
+        let mut visited = HashSet::new();
+        apply_directives(&mut resolved, &parse_file(&base, &mut visited).unwrap());
+        let mut visited = HashSet::new();
+        apply_directives(
+            &mut resolved,
+            &parse_file(&override_file, &mut visited).unwrap(),
+        );
+
+        assert_eq!(resolved.get("core.no_push"), None);
+    }
+
+    #[test]
+    fn bare_unset_removes_core_scoped_value() {
+        let dir = TempDir::new("bare-unset");
+        let base = dir.write("base", "[core]\nno_push = true\n");
+        let override_file = dir.write("override", "%unset no_push\n");
+
+        let mut resolved = HashMap::new();
+        let mut visited = HashSet::new();
+        apply_directives(&mut resolved, &parse_file(&base, &mut visited).unwrap());
+        let mut visited = HashSet::new();
+        apply_directives(
+            &mut resolved,
+            &parse_file(&override_file, &mut visited).unwrap(),
+        );
+
+        assert_eq!(resolved.get("core.no_push"), None);
+        assert_eq!(resolved.get("no_push"), None);
+    }
+
+    #[test]
+    fn include_pulls_in_relative_file() {
+        let dir = TempDir::new("include");
+        dir.write("included", "[core]\nremote = upstream\n");
+        let path = dir.write("main", "%include included\n");
+
+        let mut visited = HashSet::new();
+        let directives = parse_file(&path, &mut visited).unwrap();
+        let mut resolved = HashMap::new();
+        apply_directives(&mut resolved, &directives);
+        assert_eq!(resolved.get("core.remote"), Some(&"upstream".to_string()));
+    }
+
+    #[test]
+    fn include_cycle_is_an_error() {
+        let dir = TempDir::new("cycle");
+        let a_path = dir.write("a", "%include b\n");
+        dir.write("b", "%include a\n");
+
+        let mut visited = HashSet::new();
+        let result = parse_file(&a_path, &mut visited);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn find_repo_config_walks_up_parents() {
+        let dir = TempDir::new("walkup");
+        dir.write(".restackrc", "remote = origin\n");
+        let nested = dir.0.join("a/b/c");
+        std::fs::create_dir_all(&nested).unwrap();
+
+        let found = find_repo_config(&nested).unwrap();
+        assert_eq!(found, dir.0.join(".restackrc"));
+    }
+
+    #[test]
+    fn parse_color_rejects_unknown_name() {
+        assert!(parse_color("not-a-color").is_err());
+    }
+
+    #[test]
+    fn load_reads_repo_restackrc() {
+        let dir = TempDir::new("load-basic");
+        dir.write(
+            ".restackrc",
+            "\
+[core]
+no_push = true
+pr_fetch_limit = 50
+remote = upstream
+palette = red, green, blue
+",
+        );
+
+        let config = Config::load(&dir.0).unwrap();
+        assert_eq!(config.no_push, Some(true));
+        assert_eq!(config.pr_fetch_limit, Some(50));
+        assert_eq!(config.remote, Some("upstream".to_string()));
+        assert_eq!(
+            config.branch_palette,
+            Some(vec![Color::Red, Color::Green, Color::Blue])
+        );
+    }
+
+    #[test]
+    fn load_with_no_config_files_is_all_none() {
+        let dir = TempDir::new("load-empty");
+        let config = Config::load(&dir.0).unwrap();
+        assert_eq!(config.no_push, None);
+        assert_eq!(config.pr_fetch_limit, None);
+        assert_eq!(config.remote, None);
+        assert_eq!(config.branch_palette, None);
+    }
+
+    #[test]
+    fn load_rejects_invalid_no_push_value() {
+        let dir = TempDir::new("load-invalid-no-push");
+        dir.write(".restackrc", "[core]\nno_push = maybe\n");
+        assert!(Config::load(&dir.0).is_err());
+    }
+
+    #[test]
+    fn load_rejects_invalid_pr_fetch_limit() {
+        let dir = TempDir::new("load-invalid-limit");
+        dir.write(".restackrc", "[core]\npr_fetch_limit = not-a-number\n");
+        assert!(Config::load(&dir.0).is_err());
+    }
+
+    #[test]
+    fn load_prefers_core_scoped_value_over_bare() {
+        let dir = TempDir::new("load-precedence");
+        dir.write(
+            ".restackrc",
+            "remote = bare-value\n[core]\nremote = core-value\n",
+        );
+
+        let config = Config::load(&dir.0).unwrap();
+        assert_eq!(config.remote, Some("core-value".to_string()));
+    }
+}
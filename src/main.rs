@@ -1,10 +1,15 @@
+mod config;
+
 use anyhow::{Context, Result, bail};
 use clap::Parser;
 use clap::builder::styling::{AnsiColor, Effects, Styles};
+use config::Config;
 use console::{Color, Style, style};
 use indicatif::{ProgressBar, ProgressStyle};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
 use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
 use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::time::Duration;
@@ -28,8 +33,16 @@ struct Cli {
     dry_run: bool,
 
     /// Skip pushing branches after rebasing
-    #[arg(long)]
+    #[arg(long, conflicts_with = "push")]
     no_push: bool,
+
+    /// Push branches after rebasing, overriding a config no_push default
+    #[arg(long)]
+    push: bool,
+
+    /// Resume a stack left pending by a rebase conflict
+    #[arg(long = "continue")]
+    resume: bool,
 }
 
 fn spinner_style() -> ProgressStyle {
@@ -67,7 +80,7 @@ where
     result
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 struct PrInfo {
     number: u32,
     #[serde(rename = "headRefName")]
@@ -96,14 +109,77 @@ fn run_cmd_in(dir: &Path, cmd: &mut Command) -> Result<String> {
     run_cmd(cmd)
 }
 
-fn rebase_and_push(dir: &Path, onto: &str, no_push: bool) -> Result<()> {
+/// A branch's ahead/behind relationship to the base it would be rebased onto.
+struct Divergence {
+    up_to_date: bool,
+    ahead: u32,
+    behind: u32,
+}
+
+/// Runs `git merge-base --is-ancestor`, which signals the answer via exit
+/// code rather than stdout: 0 means yes, 1 means no, anything else is a
+/// real failure (e.g. an unknown revision).
+fn is_ancestor(ancestor: &str, descendant: &str) -> Result<bool> {
+    let status = Command::new("git")
+        .args(["merge-base", "--is-ancestor", ancestor, descendant])
+        .status()
+        .context("failed to run git merge-base")?;
+
+    match status.code() {
+        Some(0) => Ok(true),
+        Some(1) => Ok(false),
+        _ => bail!("git merge-base --is-ancestor {ancestor} {descendant} failed"),
+    }
+}
+
+/// Parses `git rev-list --left-right --count <onto>...<head_ref>` output,
+/// which is `<behind>\t<ahead>`: commits only reachable from `onto` (left),
+/// then commits only reachable from `head_ref` (right).
+fn parse_rev_list_counts(output: &str) -> Result<(u32, u32)> {
+    let mut counts = output.split_whitespace();
+    let behind: u32 = counts
+        .next()
+        .context("missing behind count in rev-list output")?
+        .parse()
+        .context("non-numeric behind count in rev-list output")?;
+    let ahead: u32 = counts
+        .next()
+        .context("missing ahead count in rev-list output")?
+        .parse()
+        .context("non-numeric ahead count in rev-list output")?;
+
+    Ok((ahead, behind))
+}
+
+fn ahead_behind(onto: &str, head_ref: &str) -> Result<(u32, u32)> {
+    let output = run_cmd(Command::new("git").args([
+        "rev-list",
+        "--left-right",
+        "--count",
+        &format!("{onto}...{head_ref}"),
+    ]))?;
+
+    parse_rev_list_counts(&output)
+}
+
+fn compute_divergence(onto: &str, head_ref: &str) -> Result<Divergence> {
+    let up_to_date = is_ancestor(onto, head_ref)?;
+    let (ahead, behind) = ahead_behind(onto, head_ref)?;
+    Ok(Divergence {
+        up_to_date,
+        ahead,
+        behind,
+    })
+}
+
+fn rebase_and_push(dir: &Path, onto: &str, no_push: bool, remote: &str) -> Result<()> {
     run_cmd_in(
         dir,
         Command::new("git").args(["rebase", "--autostash", onto]),
     )
     .with_context(|| {
         format!(
-            "resolve conflicts in {} then run: git rebase --continue && git push --force-with-lease",
+            "resolve conflicts in {} then run: git rebase --continue && git push --force-with-lease {remote}",
             dir.display()
         )
     })?;
@@ -111,42 +187,140 @@ fn rebase_and_push(dir: &Path, onto: &str, no_push: bool) -> Result<()> {
     if !no_push {
         run_cmd_in(
             dir,
-            Command::new("git").args(["push", "--force-with-lease"]),
+            Command::new("git").args(["push", remote, "--force-with-lease"]),
         )?;
     }
 
     Ok(())
 }
 
-fn rebase_in_temp_worktree(branch: &str, onto: &str, no_push: bool) -> Result<()> {
+fn temp_worktree_path(branch: &str) -> PathBuf {
     let sanitized = branch.replace('/', "-");
-    let tmp_dir = std::env::temp_dir().join(format!("restack-{sanitized}"));
+    std::env::temp_dir().join(format!("restack-{sanitized}"))
+}
+
+/// Whether `dir` (a worktree, possibly already removed) is sitting mid-rebase,
+/// i.e. `git rebase` actually hit a conflict rather than failing for some
+/// other reason (bad revision, dirty tree, a hook, disk full, ...). Checked
+/// structurally — `rebase-merge`/`rebase-apply` under the git dir — rather
+/// than by guessing from error text, since "rebase" shows up in plenty of
+/// unrelated failure messages too.
+fn rebase_in_progress(dir: &Path) -> Result<bool> {
+    if !dir.is_dir() {
+        return Ok(false);
+    }
+
+    let raw_git_dir = run_cmd_in(dir, Command::new("git").args(["rev-parse", "--git-dir"]))
+        .with_context(|| format!("failed to resolve git dir for '{}'", dir.display()))?;
+    let git_dir = PathBuf::from(&raw_git_dir);
+    let git_dir = if git_dir.is_absolute() {
+        git_dir
+    } else {
+        dir.join(git_dir)
+    };
+
+    Ok(git_dir.join("rebase-merge").is_dir() || git_dir.join("rebase-apply").is_dir())
+}
+
+fn rebase_in_temp_worktree(branch: &str, onto: &str, no_push: bool, remote: &str) -> Result<()> {
+    let tmp_dir = temp_worktree_path(branch);
     let tmp_str = tmp_dir.to_string_lossy().to_string();
 
     run_cmd(Command::new("git").args(["worktree", "add", &tmp_str, branch]))
         .with_context(|| format!("failed to create temporary worktree for branch '{branch}'"))?;
 
-    let result = rebase_and_push(&tmp_dir, onto, no_push);
+    let result = rebase_and_push(&tmp_dir, onto, no_push, remote);
 
-    match &result {
-        Ok(()) => {
-            let _ = run_cmd(Command::new("git").args(["worktree", "remove", "--force", &tmp_str]));
-        }
-        Err(e) => {
-            let msg = format!("{e:#}");
-            if msg.contains("rebase") {
-                // Rebase conflict: leave temp worktree for user to resolve
-            } else {
-                // Other failure (e.g. push): clean up since branch ref is already updated
-                let _ =
-                    run_cmd(Command::new("git").args(["worktree", "remove", "--force", &tmp_str]));
-            }
-        }
+    if result.is_ok() || !rebase_in_progress(&tmp_dir).unwrap_or(false) {
+        // Either it succeeded, or it failed for a reason other than a
+        // conflict — nothing left for the user to resolve, so clean up.
+        let _ = run_cmd(Command::new("git").args(["worktree", "remove", "--force", &tmp_str]));
     }
+    // Else: genuine conflict — leave the temp worktree for the user (or
+    // `restack --continue`) to resolve.
 
     result
 }
 
+/// Everything needed to resume a stack after a rebase conflict halts the
+/// run: the full ordered stack, which heads are already done, and the
+/// worktree the user needs to resolve before `--continue` can proceed.
+#[derive(Serialize, Deserialize, Debug)]
+struct PendingState {
+    prs: Vec<PrInfo>,
+    rebased_heads: Vec<String>,
+    no_push: bool,
+    remote: String,
+    conflicted_head_ref: String,
+    conflicted_onto: String,
+    conflicted_worktree: PathBuf,
+    conflicted_worktree_is_temp: bool,
+}
+
+/// Scoped to the repo's top-level path so two repos with a conflict pending
+/// at the same time don't clobber each other's state. There is one state
+/// file per top-level path; a new conflict there replaces the old state.
+fn state_file_path(repo_root: &Path) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    repo_root.hash(&mut hasher);
+    std::env::temp_dir().join(format!("restack-state-{:x}.json", hasher.finish()))
+}
+
+fn save_pending_state(repo_root: &Path, state: &PendingState) -> Result<()> {
+    let json = serde_json::to_string_pretty(state).context("failed to serialize pending state")?;
+    std::fs::write(state_file_path(repo_root), json).context("failed to write pending state file")
+}
+
+fn load_pending_state(repo_root: &Path) -> Result<PendingState> {
+    let path = state_file_path(repo_root);
+    let json = std::fs::read_to_string(&path).with_context(|| {
+        format!(
+            "no pending restack found at '{}' — run restack without --continue first",
+            path.display()
+        )
+    })?;
+    serde_json::from_str(&json).context("failed to parse pending state file")
+}
+
+fn clear_pending_state(repo_root: &Path) {
+    let _ = std::fs::remove_file(state_file_path(repo_root));
+}
+
+/// Resolves a rebase conflict left by `--continue`'s predecessor run: makes
+/// sure the conflicted worktree's rebase is actually finished, runs the
+/// push that was deferred when the conflict happened, and cleans up the
+/// worktree if it was a temporary one.
+fn finish_conflicted_pr(state: &PendingState) -> Result<()> {
+    if rebase_in_progress(&state.conflicted_worktree)? {
+        bail!(
+            "rebase in '{}' is still unresolved — finish it (git rebase --continue) before running restack --continue",
+            state.conflicted_worktree.display()
+        );
+    }
+
+    if !is_ancestor(&state.conflicted_onto, &state.conflicted_head_ref)? {
+        bail!(
+            "'{}' is not yet rebased onto '{}' — finish the rebase before running restack --continue",
+            state.conflicted_head_ref,
+            state.conflicted_onto
+        );
+    }
+
+    if !state.no_push {
+        run_cmd_in(
+            &state.conflicted_worktree,
+            Command::new("git").args(["push", &state.remote, "--force-with-lease"]),
+        )?;
+    }
+
+    if state.conflicted_worktree_is_temp {
+        let tmp_str = state.conflicted_worktree.to_string_lossy().to_string();
+        let _ = run_cmd(Command::new("git").args(["worktree", "remove", "--force", &tmp_str]));
+    }
+
+    Ok(())
+}
+
 fn get_pr_info(id: &str) -> Result<PrInfo> {
     let output = run_cmd(Command::new("gh").args([
         "pr",
@@ -159,14 +333,14 @@ fn get_pr_info(id: &str) -> Result<PrInfo> {
     serde_json::from_str(&output).with_context(|| format!("failed to parse PR {id} info"))
 }
 
-fn get_open_prs() -> Result<HashMap<String, PrInfo>> {
+fn get_open_prs(fetch_limit: u32) -> Result<HashMap<String, PrInfo>> {
     let output = run_cmd(Command::new("gh").args([
         "pr",
         "list",
         "--state",
         "open",
         "--limit",
-        "100",
+        &fetch_limit.to_string(),
         "--json",
         "number,headRefName,baseRefName,state",
     ]))
@@ -241,16 +415,13 @@ const BRANCH_PALETTE: &[Color] = &[
     Color::Red,
 ];
 
-fn branch_colors(prs: &[PrInfo]) -> HashMap<String, Style> {
+fn branch_colors(prs: &[PrInfo], palette: &[Color]) -> HashMap<String, Style> {
     let mut colors = HashMap::new();
     let mut idx = 0;
     for pr in prs {
         for name in [&pr.base_ref, &pr.head_ref] {
             if !colors.contains_key(name.as_str()) {
-                colors.insert(
-                    name.clone(),
-                    Style::new().fg(BRANCH_PALETTE[idx % BRANCH_PALETTE.len()]),
-                );
+                colors.insert(name.clone(), Style::new().fg(palette[idx % palette.len()]));
                 idx += 1;
             }
         }
@@ -325,11 +496,15 @@ impl StackTree {
         }
     }
 
-    fn print_colored(&self, colors: &HashMap<String, Style>) {
+    fn print_colored(
+        &self,
+        colors: &HashMap<String, Style>,
+        divergence: &HashMap<String, Divergence>,
+    ) {
         for root in &self.roots {
             println!("{}", style_branch(root, colors).bold());
             if let Some(kids) = self.children.get(root.as_str()) {
-                self.print_children_colored(kids, "", colors);
+                self.print_children_colored(kids, "", colors, divergence);
             }
         }
     }
@@ -339,14 +514,21 @@ impl StackTree {
         nodes: &[(u32, String)],
         prefix: &str,
         colors: &HashMap<String, Style>,
+        divergence: &HashMap<String, Divergence>,
     ) {
         for (i, (number, head_ref)) in nodes.iter().enumerate() {
             let is_last = i == nodes.len() - 1;
             let connector = if is_last { "└─" } else { "├─" };
             let child_prefix = if is_last { "   " } else { "│  " };
 
+            let suffix = match divergence.get(head_ref.as_str()) {
+                Some(d) if d.up_to_date => format!(" {}", style("✓ up to date").dim()),
+                Some(d) => format!(" {}", style(format!("↑{} ↓{}", d.ahead, d.behind)).dim()),
+                None => String::new(),
+            };
+
             println!(
-                "{}{} {} {}",
+                "{}{} {} {}{suffix}",
                 style(prefix).dim(),
                 style(connector).dim(),
                 style(format!("#{number}")).bold(),
@@ -354,14 +536,22 @@ impl StackTree {
             );
 
             if let Some(kids) = self.children.get(head_ref.as_str()) {
-                self.print_children_colored(kids, &format!("{prefix}{child_prefix}"), colors);
+                self.print_children_colored(
+                    kids,
+                    &format!("{prefix}{child_prefix}"),
+                    colors,
+                    divergence,
+                );
             }
         }
     }
 }
 
-fn discover_worktree_prs(worktree_map: &HashMap<String, PathBuf>) -> Result<Vec<PrInfo>> {
-    let open_prs = with_spinner("Fetching open PRs", get_open_prs)?;
+fn discover_worktree_prs(
+    worktree_map: &HashMap<String, PathBuf>,
+    pr_fetch_limit: u32,
+) -> Result<Vec<PrInfo>> {
+    let open_prs = with_spinner("Fetching open PRs", || get_open_prs(pr_fetch_limit))?;
     let mut prs = Vec::new();
     let mut seen = HashSet::new();
 
@@ -380,12 +570,199 @@ fn discover_worktree_prs(worktree_map: &HashMap<String, PathBuf>) -> Result<Vec<
     Ok(prs)
 }
 
+/// The `<remote>/<base>` (or just `<base>` once the base has itself been
+/// rebased locally) that `pr` should be rebased onto.
+fn onto_for(pr: &PrInfo, rebased_heads: &HashSet<String>, remote: &str) -> String {
+    if rebased_heads.contains(&pr.base_ref) {
+        pr.base_ref.clone()
+    } else {
+        format!("{remote}/{}", pr.base_ref)
+    }
+}
+
+fn step_message(
+    pr: &PrInfo,
+    rebased_heads: &HashSet<String>,
+    colors: &HashMap<String, Style>,
+    remote: &str,
+) -> String {
+    let onto_styled = if rebased_heads.contains(&pr.base_ref) {
+        format!("{}", style_branch(&pr.base_ref, colors))
+    } else {
+        format!(
+            "{}{}",
+            style(format!("{remote}/")).dim(),
+            style_branch(&pr.base_ref, colors)
+        )
+    };
+
+    format!(
+        "{} {} → {}",
+        style(format!("#{}", pr.number)).bold(),
+        style_branch(&pr.head_ref, colors),
+        onto_styled,
+    )
+}
+
+/// Rebases every PR in `prs` not already in `rebased_heads`, in order,
+/// skipping (but marking done) any that are already up to date with the
+/// base they would be rebased onto. On a
+/// rebase conflict, persists enough state for `restack --continue` to pick
+/// up where this left off and returns an error describing that.
+fn run_stack(
+    repo_root: &Path,
+    prs: &[PrInfo],
+    worktree_map: &HashMap<String, PathBuf>,
+    colors: &HashMap<String, Style>,
+    remote: &str,
+    no_push: bool,
+    rebased_heads: &mut HashSet<String>,
+) -> Result<()> {
+    for pr in prs {
+        if rebased_heads.contains(&pr.head_ref) {
+            continue;
+        }
+
+        let onto = onto_for(pr, rebased_heads, remote);
+        let msg = step_message(pr, rebased_heads, colors, remote);
+
+        // Re-check against `onto` every iteration: once a parent has
+        // actually been rebased, a child that looked up to date against
+        // <remote>/<base> may no longer be up to date against the
+        // freshly-rebased local base.
+        let up_to_date = compute_divergence(&onto, &pr.head_ref)?.up_to_date;
+
+        if up_to_date {
+            println!(
+                "{} {} {}",
+                style("✔").green().bold(),
+                msg,
+                style("(up to date)").dim()
+            );
+        } else {
+            let result = match worktree_map.get(&pr.head_ref) {
+                Some(worktree_path) => with_spinner(&msg, || {
+                    rebase_and_push(worktree_path, &onto, no_push, remote)
+                }),
+                None => {
+                    let head_ref = pr.head_ref.clone();
+                    let onto_owned = onto.clone();
+                    let remote_owned = remote.to_string();
+                    with_spinner(&msg, move || {
+                        rebase_in_temp_worktree(&head_ref, &onto_owned, no_push, &remote_owned)
+                    })
+                }
+            };
+
+            if let Err(e) = result {
+                let (conflicted_worktree, conflicted_worktree_is_temp) =
+                    match worktree_map.get(&pr.head_ref) {
+                        Some(path) => (path.clone(), false),
+                        None => (temp_worktree_path(&pr.head_ref), true),
+                    };
+
+                if rebase_in_progress(&conflicted_worktree).unwrap_or(false) {
+                    save_pending_state(
+                        repo_root,
+                        &PendingState {
+                            prs: prs.to_vec(),
+                            rebased_heads: rebased_heads.iter().cloned().collect(),
+                            no_push,
+                            remote: remote.to_string(),
+                            conflicted_head_ref: pr.head_ref.clone(),
+                            conflicted_onto: onto.clone(),
+                            conflicted_worktree,
+                            conflicted_worktree_is_temp,
+                        },
+                    )?;
+                    bail!("{e:#}\n\nResolve the conflict, then run: restack --continue");
+                }
+
+                return Err(e);
+            }
+        }
+
+        rebased_heads.insert(pr.head_ref.clone());
+    }
+
+    Ok(())
+}
+
+/// Resumes a stack that a previous run left pending after a rebase
+/// conflict: finishes up the conflicted branch (verifying its rebase is
+/// actually done, running the deferred push, cleaning up a temp worktree),
+/// then continues through the rest of the stack exactly as a fresh run
+/// would, skipping branches already marked done.
+fn run_continue() -> Result<()> {
+    let repo_root = PathBuf::from(run_cmd(
+        Command::new("git").args(["rev-parse", "--show-toplevel"]),
+    )?);
+
+    let state = load_pending_state(&repo_root)?;
+    finish_conflicted_pr(&state)?;
+
+    let mut rebased_heads: HashSet<String> = state.rebased_heads.iter().cloned().collect();
+    rebased_heads.insert(state.conflicted_head_ref.clone());
+
+    let config = Config::load(&repo_root)?;
+    let palette: Vec<Color> = config
+        .branch_palette
+        .unwrap_or_else(|| BRANCH_PALETTE.to_vec());
+    let colors = branch_colors(&state.prs, &palette);
+    let worktree_map = get_worktree_map()?;
+
+    run_stack(
+        &repo_root,
+        &state.prs,
+        &worktree_map,
+        &colors,
+        &state.remote,
+        state.no_push,
+        &mut rebased_heads,
+    )?;
+
+    clear_pending_state(&repo_root);
+    println!("\nAll PRs restacked successfully.");
+    Ok(())
+}
+
 fn main() -> Result<()> {
     let cli = Cli::parse();
+
+    if cli.resume {
+        if !cli.prs.is_empty() {
+            bail!("--continue does not take PR numbers");
+        }
+        if cli.dry_run {
+            bail!("--continue cannot be combined with --dry-run");
+        }
+        return run_continue();
+    }
+
+    let repo_root = PathBuf::from(run_cmd(
+        Command::new("git").args(["rev-parse", "--show-toplevel"]),
+    )?);
+    let config = Config::load(&repo_root)?;
+
+    // An explicit --no-push/--push always wins over the config default,
+    // in either direction (clap rejects passing both via conflicts_with).
+    let no_push = if cli.push {
+        false
+    } else if cli.no_push {
+        true
+    } else {
+        config.no_push.unwrap_or(false)
+    };
+    let pr_fetch_limit = config.pr_fetch_limit.unwrap_or(100);
+    let remote = config.remote.unwrap_or_else(|| "origin".to_string());
+    let palette: Vec<Color> = config
+        .branch_palette
+        .unwrap_or_else(|| BRANCH_PALETTE.to_vec());
+
     let worktree_map = get_worktree_map()?;
 
     let prs = if cli.prs.is_empty() {
-        discover_worktree_prs(&worktree_map)?
+        discover_worktree_prs(&worktree_map, pr_fetch_limit)?
     } else {
         let mut seen = HashSet::new();
         let pr_numbers: Vec<u32> = cli.prs.into_iter().filter(|n| seen.insert(*n)).collect();
@@ -405,72 +782,59 @@ fn main() -> Result<()> {
     };
 
     let prs = sort_by_dependency(prs)?;
-    let colors = branch_colors(&prs);
-    StackTree::build(&prs).print_colored(&colors);
+    let colors = branch_colors(&prs, &palette);
 
     if !cli.dry_run {
-        with_spinner("Fetching origin", || {
-            run_cmd(Command::new("git").args(["fetch", "origin"]))?;
+        with_spinner(&format!("Fetching {remote}"), || {
+            run_cmd(Command::new("git").args(["fetch", &remote]))?;
             Ok(())
         })?;
     }
 
-    println!();
-
-    let mut rebased_heads: HashSet<String> = HashSet::new();
+    let divergence = if cli.dry_run {
+        HashMap::new()
+    } else {
+        prs.iter()
+            .map(|pr| {
+                let onto = format!("{remote}/{}", pr.base_ref);
+                Ok((
+                    pr.head_ref.clone(),
+                    compute_divergence(&onto, &pr.head_ref)?,
+                ))
+            })
+            .collect::<Result<HashMap<_, _>>>()?
+    };
 
-    for pr in &prs {
-        // Rebase onto local branch if it was just rebased, otherwise onto origin/<base>
-        let onto = if rebased_heads.contains(&pr.base_ref) {
-            pr.base_ref.clone()
-        } else {
-            format!("origin/{}", pr.base_ref)
-        };
+    StackTree::build(&prs).print_colored(&colors, &divergence);
 
-        let onto_styled = if rebased_heads.contains(&pr.base_ref) {
-            format!("{}", style_branch(&pr.base_ref, &colors))
-        } else {
-            format!(
-                "{}{}",
-                style("origin/").dim(),
-                style_branch(&pr.base_ref, &colors)
-            )
-        };
-
-        let msg = format!(
-            "{} {} → {}",
-            style(format!("#{}", pr.number)).bold(),
-            style_branch(&pr.head_ref, &colors),
-            onto_styled,
-        );
+    println!();
 
-        if cli.dry_run {
-            let push_note = if cli.no_push { "" } else { " + push" };
+    if cli.dry_run {
+        let mut rebased_heads: HashSet<String> = HashSet::new();
+        for pr in &prs {
+            let msg = step_message(pr, &rebased_heads, &colors, &remote);
+            let push_note = if no_push { "" } else { " + push" };
             println!("  {msg}{push_note}");
-        } else {
-            let no_push = cli.no_push;
-            match worktree_map.get(&pr.head_ref) {
-                Some(worktree_path) => {
-                    with_spinner(&msg, || rebase_and_push(worktree_path, &onto, no_push))?;
-                }
-                None => {
-                    let head_ref = pr.head_ref.clone();
-                    with_spinner(&msg, move || {
-                        rebase_in_temp_worktree(&head_ref, &onto, no_push)
-                    })?;
-                }
-            }
+            rebased_heads.insert(pr.head_ref.clone());
         }
-
-        rebased_heads.insert(pr.head_ref.clone());
-    }
-
-    if cli.dry_run {
         println!("\n(dry run — no changes made)");
-    } else {
-        println!("\nAll PRs restacked successfully.");
+        return Ok(());
     }
 
+    let mut rebased_heads: HashSet<String> = HashSet::new();
+    run_stack(
+        &repo_root,
+        &prs,
+        &worktree_map,
+        &colors,
+        &remote,
+        no_push,
+        &mut rebased_heads,
+    )?;
+
+    clear_pending_state(&repo_root);
+    println!("\nAll PRs restacked successfully.");
+
     Ok(())
 }
 
@@ -602,4 +966,24 @@ branch refs/heads/feature/feat-b
             Some(&PathBuf::from("/Users/raine/code/myrepo__worktrees/feat-b"))
         );
     }
+
+    #[test]
+    fn parse_rev_list_counts_is_behind_then_ahead() {
+        let (ahead, behind) = parse_rev_list_counts("3\t1\n").unwrap();
+        assert_eq!(ahead, 1);
+        assert_eq!(behind, 3);
+    }
+
+    #[test]
+    fn parse_rev_list_counts_zero_zero_means_up_to_date() {
+        let (ahead, behind) = parse_rev_list_counts("0\t0").unwrap();
+        assert_eq!(ahead, 0);
+        assert_eq!(behind, 0);
+    }
+
+    #[test]
+    fn parse_rev_list_counts_rejects_malformed_output() {
+        assert!(parse_rev_list_counts("not-a-number\t1").is_err());
+        assert!(parse_rev_list_counts("").is_err());
+    }
 }